@@ -1,7 +1,9 @@
-use std::cell::OnceCell;
+use std::collections::HashSet;
 use std::env;
 use std::fs::{self, File};
+use std::io;
 use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
 
 use memmap2::Mmap;
 use typst::diag::StrResult;
@@ -35,6 +37,13 @@ pub struct FontSearcher {
     pub book: FontBook,
     /// Slots that the fonts are loaded into.
     pub fonts: Vec<FontSlot>,
+    /// Canonical paths of all font files indexed so far. Used to avoid
+    /// indexing the same file twice when it is reachable through multiple
+    /// search directories or symlinks.
+    seen: HashSet<PathBuf>,
+    /// Hashes of the contents of all font files indexed so far. Used to avoid
+    /// indexing identical fonts that are installed in multiple places.
+    hashes: HashSet<u128>,
 }
 
 /// Holds details about the location of a font and lazily the font itself.
@@ -44,8 +53,9 @@ pub struct FontSlot {
     /// The index of the font in its collection. Zero if the path does not point
     /// to a collection.
     index: u32,
-    /// The lazily loaded font.
-    font: OnceCell<Option<Font>>,
+    /// The lazily loaded font. This is a `OnceLock` so that slots can be
+    /// shared with threads that load fonts concurrently.
+    font: OnceLock<Option<Font>>,
 }
 
 impl FontSlot {
@@ -53,8 +63,22 @@ impl FontSlot {
     pub fn get(&self) -> Option<Font> {
         self.font
             .get_or_init(|| {
-                let data = fs::read(&self.path).ok()?.into();
-                Font::new(data, self.index)
+                tracing::debug!("Loading font {} ({})", self.path.display(), self.index);
+                let data = match fs::read(&self.path) {
+                    Ok(data) => data.into(),
+                    Err(err) => {
+                        tracing::warn!(
+                            "Failed to read font {}: {err}",
+                            self.path.display()
+                        );
+                        return None;
+                    }
+                };
+                let font = Font::new(data, self.index);
+                if font.is_none() {
+                    tracing::warn!("Failed to load font {}", self.path.display());
+                }
+                font
             })
             .clone()
     }
@@ -63,11 +87,22 @@ impl FontSlot {
 impl FontSearcher {
     /// Create a new, empty system searcher.
     pub fn new() -> Self {
-        Self { book: FontBook::new(), fonts: vec![] }
+        Self {
+            book: FontBook::new(),
+            fonts: vec![],
+            seen: HashSet::new(),
+            hashes: HashSet::new(),
+        }
     }
 
     /// Search everything that is available.
+    #[tracing::instrument(skip_all)]
     pub fn search(&mut self, font_paths: &[PathBuf]) {
+        for (path, err) in unreadable_paths(font_paths) {
+            let msg = format!("font path {} could not be read: {err}", path.display());
+            crate::print_warning(&msg).expect("failed to print warning");
+        }
+
         for path in font_paths {
             self.search_dir(path)
         }
@@ -76,6 +111,8 @@ impl FontSearcher {
 
         #[cfg(feature = "embed-fonts")]
         self.add_embedded();
+
+        tracing::info!("Found {} fonts", self.fonts.len());
     }
 
     /// Add fonts that are embedded in the binary.
@@ -88,7 +125,7 @@ impl FontSearcher {
                 self.fonts.push(FontSlot {
                     path: PathBuf::new(),
                     index: i as u32,
-                    font: OnceCell::from(Some(font)),
+                    font: OnceLock::from(Some(font)),
                 });
             }
         };
@@ -156,9 +193,13 @@ impl FontSearcher {
             .filter_map(|e| e.ok())
         {
             let path = entry.path();
+            let extension = path
+                .extension()
+                .and_then(|s| s.to_str())
+                .map(|s| s.to_ascii_lowercase());
             if matches!(
-                path.extension().and_then(|s| s.to_str()),
-                Some("ttf" | "otf" | "TTF" | "OTF" | "ttc" | "otc" | "TTC" | "OTC"),
+                extension.as_deref(),
+                Some("ttf" | "otf" | "ttc" | "otc" | "woff"),
             ) {
                 self.search_file(path);
             }
@@ -167,17 +208,147 @@ impl FontSearcher {
 
     /// Index the fonts in the file at the given path.
     fn search_file(&mut self, path: &Path) {
+        let canonical = path.canonicalize().unwrap_or_else(|_| path.into());
+        if !self.seen.insert(canonical) {
+            return;
+        }
+
         if let Ok(file) = File::open(path) {
             if let Ok(mmap) = unsafe { Mmap::map(&file) } {
+                if !self.hashes.insert(typst::util::hash128(&mmap[..])) {
+                    return;
+                }
+
                 for (i, info) in FontInfo::iter(&mmap).enumerate() {
                     self.book.push(info);
                     self.fonts.push(FontSlot {
                         path: path.into(),
                         index: i as u32,
-                        font: OnceCell::new(),
+                        font: OnceLock::new(),
                     });
                 }
             }
         }
     }
 }
+
+/// The user-provided font paths that don't exist or can't be read, together
+/// with the error that occurred.
+fn unreadable_paths(font_paths: &[PathBuf]) -> impl Iterator<Item = (&Path, io::Error)> {
+    font_paths.iter().filter_map(|path| {
+        let result = fs::metadata(path).and_then(|metadata| {
+            if metadata.is_dir() {
+                fs::read_dir(path).map(|_| ())
+            } else {
+                File::open(path).map(|_| ())
+            }
+        });
+        result.err().map(|err| (path.as_path(), err))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A font shipped in the repository's assets.
+    fn asset(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../../assets/fonts")
+            .join(name)
+    }
+
+    #[test]
+    fn test_font_slot_shared_across_threads() {
+        fn assert_sync<T: Sync>() {}
+        assert_sync::<FontSlot>();
+
+        let slot = FontSlot {
+            path: asset("IBMPlexSans-Regular.ttf"),
+            index: 0,
+            font: OnceLock::new(),
+        };
+
+        let fonts: Vec<Option<Font>> = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..4).map(|_| scope.spawn(|| slot.get())).collect();
+            handles.into_iter().map(|handle| handle.join().unwrap()).collect()
+        });
+
+        assert!(fonts[0].is_some());
+        assert!(fonts.iter().all(|font| *font == fonts[0]));
+    }
+
+    #[test]
+    fn test_unreadable_paths_are_reported() {
+        let missing = asset("does-not-exist");
+        let paths = [asset(""), asset("IBMPlexSans-Regular.ttf"), missing.clone()];
+        let unreadable: Vec<_> = unreadable_paths(&paths).collect();
+        assert_eq!(unreadable.len(), 1);
+        assert_eq!(unreadable[0].0, missing);
+        assert_eq!(unreadable[0].1.kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_unreadable_dir_is_reported() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let locked = dir.path().join("locked");
+        fs::create_dir(&locked).unwrap();
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o000)).unwrap();
+
+        // Privileged users can read the directory anyway.
+        let expected = usize::from(fs::read_dir(&locked).is_err());
+        let paths = [locked.clone()];
+        let unreadable: Vec<_> = unreadable_paths(&paths).collect();
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o755)).unwrap();
+
+        assert_eq!(unreadable.len(), expected);
+        if let Some((path, err)) = unreadable.first() {
+            assert_eq!(*path, locked);
+            assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+        }
+    }
+
+    #[test]
+    fn test_search_dir_twice() {
+        let dir = asset("");
+        let mut searcher = FontSearcher::new();
+        searcher.search_dir(&dir);
+        let count = searcher.fonts.len();
+        assert!(count > 0);
+        assert!(searcher.book.info(count - 1).is_some());
+
+        searcher.search_dir(&dir);
+        assert_eq!(searcher.fonts.len(), count);
+        assert!(searcher.book.info(count).is_none());
+    }
+
+    #[test]
+    fn test_identical_fonts_are_deduplicated() {
+        let data = fs::read(asset("IBMPlexSans-Regular.ttf")).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.ttf"), &data).unwrap();
+        fs::write(dir.path().join("b.ttf"), &data).unwrap();
+
+        let mut searcher = FontSearcher::new();
+        searcher.search_dir(dir.path());
+        assert_eq!(searcher.fonts.len(), 1);
+        assert!(searcher.book.info(1).is_none());
+    }
+
+    #[test]
+    fn test_font_index_out_of_range() {
+        let mut searcher = FontSearcher::new();
+        searcher.search_file(&asset("IBMPlexSans-Regular.ttf"));
+
+        // This is how `SystemWorld::font` looks fonts up.
+        let font = |index: usize| searcher.fonts.get(index)?.get();
+        let len = searcher.fonts.len();
+        assert_eq!(len, 1);
+        assert!(font(0).is_some());
+        assert!(font(len).is_none());
+        assert!(font(usize::MAX).is_none());
+    }
+}
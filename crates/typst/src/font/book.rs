@@ -5,7 +5,7 @@ use serde::{Deserialize, Serialize};
 use ttf_parser::{name_id, PlatformId, Tag};
 use unicode_segmentation::UnicodeSegmentation;
 
-use super::{Font, FontStretch, FontStyle, FontVariant, FontWeight};
+use super::{woff, Font, FontFormat, FontStretch, FontStyle, FontVariant, FontWeight};
 
 /// Metadata about a collection of fonts.
 #[derive(Default, Clone, Hash)]
@@ -87,14 +87,28 @@ impl FontBook {
         variant: FontVariant,
         text: &str,
     ) -> Option<usize> {
-        // Find the fonts that contain the text's first char ...
-        let c = text.chars().next()?;
-        let ids = self
-            .infos
-            .iter()
-            .enumerate()
-            .filter(|(_, info)| info.coverage.contains(c as u32))
-            .map(|(index, _)| index);
+        // Find the fonts that contain the text's first grapheme cluster, so
+        // that a base character and its combining marks end up in the same
+        // font. If no font covers the full cluster, we settle for fonts that
+        // contain at least its first char ...
+        let cluster = text.graphemes(true).next()?;
+        let mut chars = cluster.chars();
+        let first = chars.next()?;
+        let rest = chars.as_str();
+
+        // Collect both lists in a single pass over the fonts.
+        let mut full = vec![];
+        let mut partial = vec![];
+        for (index, info) in self.infos.iter().enumerate() {
+            if info.coverage.contains(first as u32) {
+                partial.push(index);
+                if rest.chars().all(|c| info.coverage.contains(c as u32)) {
+                    full.push(index);
+                }
+            }
+        }
+
+        let ids = if full.is_empty() { partial } else { full };
 
         // ... and find the best variant among them.
         self.find_best_variant(like, variant, ids)
@@ -188,11 +202,26 @@ bitflags::bitflags! {
 impl FontInfo {
     /// Compute metadata for all fonts in the given data.
     pub fn iter(data: &[u8]) -> impl Iterator<Item = FontInfo> + '_ {
-        let count = ttf_parser::fonts_in_collection(data).unwrap_or(1);
-        (0..count).filter_map(move |index| {
+        // WOFF files are never collections, so we decode them eagerly.
+        let format = FontFormat::detect(data);
+        let decoded = (format == Some(FontFormat::Woff))
+            .then(|| woff::decode(data))
+            .flatten()
+            .and_then(|sfnt| {
+                let ttf = ttf_parser::Face::parse(&sfnt, 0).ok()?;
+                Self::from_ttf(&ttf)
+            });
+
+        let count = match format {
+            Some(
+                FontFormat::TrueType | FontFormat::OpenTypeCff | FontFormat::Collection,
+            ) => ttf_parser::fonts_in_collection(data).unwrap_or(1),
+            _ => 0,
+        };
+        decoded.into_iter().chain((0..count).filter_map(move |index| {
             let ttf = ttf_parser::Face::parse(data, index).ok()?;
             Self::from_ttf(&ttf)
-        })
+        }))
     }
 
     /// Compute metadata for a single ttf-parser face.
@@ -242,6 +271,21 @@ impl FontInfo {
 
             let weight = {
                 let mut number = ttf.weight().to_number();
+
+                // Fonts without an OS/2 table don't declare a weight at all,
+                // so we try to infer it from the subfamily name. The full name
+                // would also include the family, which might contain a weight
+                // by accident (e.g. "Blackadder ITC").
+                if ttf.tables().os2.is_none() {
+                    let mut subfamily = find_name(ttf, name_id::TYPOGRAPHIC_SUBFAMILY)
+                        .or_else(|| find_name(ttf, name_id::SUBFAMILY))
+                        .unwrap_or_default();
+                    subfamily.make_ascii_lowercase();
+                    if let Some(weight) = infer_weight(&subfamily) {
+                        number = weight.to_number();
+                    }
+                }
+
                 if (family.starts_with("NewCM")
                     || family.starts_with("New Computer Modern"))
                     && full.contains("book")
@@ -286,6 +330,36 @@ impl FontInfo {
     }
 }
 
+/// Infer a font's weight from its lowercased subfamily name.
+fn infer_weight(subfamily: &str) -> Option<FontWeight> {
+    // Compound names must come before their suffixes.
+    const WEIGHTS: &[(&str, u16)] = &[
+        ("hairline", 100),
+        ("thin", 100),
+        ("extralight", 200),
+        ("ultralight", 200),
+        ("semilight", 350),
+        ("demilight", 350),
+        ("semibold", 600),
+        ("demibold", 600),
+        ("extrabold", 800),
+        ("ultrabold", 800),
+        ("black", 900),
+        ("heavy", 900),
+        ("light", 300),
+        ("medium", 500),
+        ("bold", 700),
+    ];
+
+    // Ignore separators so that "Extra Light" and "Extra-Light" match, too.
+    let compact: String =
+        subfamily.chars().filter(|c| !matches!(c, ' ' | '-' | '_')).collect();
+    WEIGHTS
+        .iter()
+        .find(|(name, _)| compact.contains(name))
+        .map(|&(_, number)| FontWeight::from_number(number))
+}
+
 /// Try to find and decode the name with the given id.
 pub(super) fn find_name(ttf: &ttf_parser::Face, name_id: u16) -> Option<String> {
     ttf.names().into_iter().find_map(|entry| {
@@ -512,6 +586,129 @@ mod tests {
         assert_eq!(typographic_family("Font Ultra Bold"), "Font");
     }
 
+    #[test]
+    fn test_infer_weight() {
+        assert_eq!(infer_weight("bolditalic"), Some(FontWeight::BOLD));
+        assert_eq!(infer_weight("extra light"), Some(FontWeight::EXTRALIGHT));
+        assert_eq!(infer_weight("semibold"), Some(FontWeight::SEMIBOLD));
+        assert_eq!(infer_weight("black_oblique"), Some(FontWeight::BLACK));
+        assert_eq!(infer_weight("light"), Some(FontWeight::LIGHT));
+        assert_eq!(infer_weight("semilight"), Some(FontWeight::from_number(350)));
+        assert_eq!(infer_weight("demi-light italic"), Some(FontWeight::from_number(350)));
+        assert_eq!(infer_weight("italic"), None);
+        assert_eq!(infer_weight("regular"), None);
+    }
+
+    /// Rebuild an sfnt font program without its OS/2 table and with a name
+    /// table that only holds the given names.
+    fn without_os2(sfnt: &[u8], names: &[(u16, &str)]) -> Vec<u8> {
+        let read =
+            |pos: usize| u32::from_be_bytes(sfnt[pos..pos + 4].try_into().unwrap());
+        let num_tables = read(4) >> 16;
+
+        let mut name = vec![];
+        let mut strings = vec![];
+        for field in [0, names.len() as u16, 6 + 12 * names.len() as u16] {
+            name.extend(field.to_be_bytes());
+        }
+        for &(id, string) in names {
+            let encoded: Vec<u8> =
+                string.encode_utf16().flat_map(u16::to_be_bytes).collect();
+            for field in [3, 1, 0x409, id, encoded.len() as u16, strings.len() as u16] {
+                name.extend(field.to_be_bytes());
+            }
+            strings.extend(encoded);
+        }
+        name.extend(strings);
+
+        let mut tables = vec![];
+        for i in 0..num_tables as usize {
+            let pos = 12 + i * 16;
+            let tag = &sfnt[pos..pos + 4];
+            let (start, len) = (read(pos + 8) as usize, read(pos + 12) as usize);
+            match tag {
+                b"OS/2" => {}
+                b"name" => tables.push((tag, name.clone())),
+                _ => tables.push((tag, sfnt[start..start + len].to_vec())),
+            }
+        }
+
+        // The search parameters are left zeroed since nobody reads them.
+        let mut out = sfnt[..4].to_vec();
+        out.extend((tables.len() as u16).to_be_bytes());
+        out.extend([0; 6]);
+        let mut offset = 12 + tables.len() * 16;
+        for (tag, table) in &tables {
+            out.extend(*tag);
+            out.extend([0; 4]);
+            out.extend((offset as u32).to_be_bytes());
+            out.extend((table.len() as u32).to_be_bytes());
+            offset += (table.len() + 3) & !3;
+        }
+        for (_, table) in &tables {
+            out.extend(table);
+            out.resize((out.len() + 3) & !3, 0);
+        }
+        out
+    }
+
+    #[test]
+    fn test_infer_weight_without_os2() {
+        const FONT: &[u8] =
+            include_bytes!("../../../../assets/fonts/IBMPlexSans-Regular.ttf");
+        let info = |names: &[(u16, &str)]| {
+            FontInfo::iter(&without_os2(FONT, names)).next().unwrap()
+        };
+
+        let foo = info(&[
+            (name_id::FAMILY, "Foo"),
+            (name_id::SUBFAMILY, "Bold Italic"),
+            (name_id::FULL_NAME, "Foo-BoldItalic"),
+        ]);
+        assert_eq!(foo.family, "Foo");
+        assert_eq!(foo.variant.weight, FontWeight::BOLD);
+        assert_eq!(foo.variant.style, FontStyle::Italic);
+
+        // A weight in the family name must not leak into the variant.
+        let blackadder = info(&[
+            (name_id::FAMILY, "Blackadder ITC"),
+            (name_id::SUBFAMILY, "Regular"),
+            (name_id::FULL_NAME, "Blackadder ITC"),
+        ]);
+        assert_eq!(blackadder.variant.weight, FontWeight::REGULAR);
+        assert_eq!(blackadder.variant.style, FontStyle::Normal);
+
+        // The typographic subfamily takes precedence.
+        let semilight = info(&[
+            (name_id::FAMILY, "Foo SemiLight"),
+            (name_id::SUBFAMILY, "Regular"),
+            (name_id::FULL_NAME, "Foo SemiLight"),
+            (name_id::TYPOGRAPHIC_SUBFAMILY, "SemiLight"),
+        ]);
+        assert_eq!(semilight.variant.weight, FontWeight::from_number(350));
+    }
+
+    #[test]
+    fn test_select_fallback_cluster() {
+        let info = |family: &str, codepoints: &[u32]| FontInfo {
+            family: family.into(),
+            variant: FontVariant::default(),
+            flags: FontFlags::empty(),
+            coverage: Coverage::from_vec(codepoints.to_vec()),
+        };
+
+        let mut book = FontBook::new();
+        book.push(info("Base", &['e' as u32, 'x' as u32]));
+        book.push(info("Marks", &['e' as u32, 0x301]));
+
+        let select = |text| book.select_fallback(None, FontVariant::default(), text);
+        assert_eq!(select("e"), Some(0));
+        assert_eq!(select("x\u{301}"), Some(0));
+        assert_eq!(select("e\u{301}"), Some(1));
+        assert_eq!(select("e\u{302}"), Some(0));
+        assert_eq!(select("z"), None);
+    }
+
     #[test]
     fn test_coverage() {
         #[track_caller]
@@ -2,6 +2,7 @@
 
 mod book;
 mod variant;
+mod woff;
 
 pub use self::book::{Coverage, FontBook, FontFlags, FontInfo};
 pub use self::variant::{FontStretch, FontStyle, FontVariant, FontWeight};
@@ -10,7 +11,8 @@ use std::fmt::{self, Debug, Formatter};
 use std::hash::{Hash, Hasher};
 use std::sync::Arc;
 
-use ttf_parser::GlyphId;
+use ttf_parser::opentype_layout::LayoutTable;
+use ttf_parser::{GlyphId, Tag};
 
 use self::book::find_name;
 use crate::eval::{Bytes, Cast};
@@ -42,7 +44,16 @@ struct Repr {
 
 impl Font {
     /// Parse a font from data and collection index.
+    ///
+    /// WOFF-compressed data is transparently decompressed. In this case, the
+    /// font's [data](Self::data) is the decompressed font program.
     pub fn new(data: Bytes, index: u32) -> Option<Self> {
+        let data = match FontFormat::detect(&data)? {
+            FontFormat::Woff => woff::decode(&data)?.into(),
+            FontFormat::Woff2 => return None,
+            _ => data,
+        };
+
         // Safety:
         // - The slices's location is stable in memory:
         //   - We don't move the underlying vector
@@ -104,6 +115,42 @@ impl Font {
             .map(|units| self.to_em(units))
     }
 
+    /// Whether the font's GSUB or GPOS table declares the OpenType feature
+    /// with the given tag.
+    pub fn has_feature(&self, tag: Tag) -> bool {
+        let tables = self.0.ttf.tables();
+        [tables.gsub, tables.gpos]
+            .into_iter()
+            .flatten()
+            .any(|table| table.features.find(tag).is_some())
+    }
+
+    /// The tags of all OpenType features declared in the font's GSUB and GPOS
+    /// tables, sorted and without duplicates.
+    pub fn features(&self) -> Vec<Tag> {
+        self.layout_tags(|table| table.features.into_iter().map(|feature| feature.tag))
+    }
+
+    /// The tags of all OpenType scripts declared in the font's GSUB and GPOS
+    /// tables, sorted and without duplicates.
+    pub fn scripts(&self) -> Vec<Tag> {
+        self.layout_tags(|table| table.scripts.into_iter().map(|script| script.tag))
+    }
+
+    /// Collect the tags that `f` yields for the GSUB and GPOS tables, sorted
+    /// and without duplicates.
+    fn layout_tags<I>(&self, f: impl FnMut(LayoutTable<'static>) -> I) -> Vec<Tag>
+    where
+        I: IntoIterator<Item = Tag>,
+    {
+        let tables = self.0.ttf.tables();
+        let mut tags: Vec<Tag> =
+            [tables.gsub, tables.gpos].into_iter().flatten().flat_map(f).collect();
+        tags.sort();
+        tags.dedup();
+        tags
+    }
+
     /// Lookup a name by id.
     pub fn find_name(&self, id: u16) -> Option<String> {
         find_name(&self.0.ttf, id)
@@ -145,6 +192,38 @@ impl PartialEq for Font {
     }
 }
 
+/// The container format of font data.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum FontFormat {
+    /// A single font with TrueType outlines.
+    TrueType,
+    /// A single font with CFF outlines.
+    OpenTypeCff,
+    /// A collection of multiple fonts.
+    Collection,
+    /// A WOFF 1.0 compressed font.
+    Woff,
+    /// A WOFF 2.0 compressed font. Not supported for parsing.
+    Woff2,
+}
+
+impl FontFormat {
+    /// Detect the format of font data from its leading signature.
+    ///
+    /// This is much cheaper than parsing and can be used to reject non-font
+    /// data early. Returns `None` if the signature is unknown.
+    pub fn detect(data: &[u8]) -> Option<Self> {
+        match data.get(..4)? {
+            b"\0\x01\0\0" | b"true" => Some(Self::TrueType),
+            b"OTTO" => Some(Self::OpenTypeCff),
+            b"ttcf" => Some(Self::Collection),
+            b"wOFF" => Some(Self::Woff),
+            b"wOF2" => Some(Self::Woff2),
+            _ => None,
+        }
+    }
+}
+
 /// Metrics of a font.
 #[derive(Debug, Copy, Clone)]
 pub struct FontMetrics {
@@ -246,3 +325,50 @@ pub enum VerticalFontMetric {
     /// The font's ascender, which typically exceeds the depth of all glyphs.
     Descender,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Load a font from the repository's assets.
+    fn font(data: &'static [u8]) -> Font {
+        Font::new(data.into(), 0).unwrap()
+    }
+
+    #[test]
+    fn test_font_features() {
+        let font =
+            font(include_bytes!("../../../../assets/fonts/IBMPlexSans-Regular.ttf"));
+        assert!(font.has_feature(Tag::from_bytes(b"liga")));
+        assert!(font.has_feature(Tag::from_bytes(b"kern")));
+        assert!(!font.has_feature(Tag::from_bytes(b"zzzz")));
+
+        let features = font.features();
+        assert!(features.contains(&Tag::from_bytes(b"liga")));
+        assert!(features.contains(&Tag::from_bytes(b"kern")));
+        assert!(features.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn test_font_scripts() {
+        let font =
+            font(include_bytes!("../../../../assets/fonts/IBMPlexSans-Regular.ttf"));
+        let scripts = font.scripts();
+        assert!(scripts.contains(&Tag::from_bytes(b"latn")));
+        assert!(scripts.contains(&Tag::from_bytes(b"grek")));
+        assert!(!scripts.contains(&Tag::from_bytes(b"hebr")));
+        assert!(scripts.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn test_font_format_detect() {
+        assert_eq!(FontFormat::detect(&[0, 1, 0, 0, 0, 9]), Some(FontFormat::TrueType));
+        assert_eq!(FontFormat::detect(b"true...."), Some(FontFormat::TrueType));
+        assert_eq!(FontFormat::detect(b"OTTO...."), Some(FontFormat::OpenTypeCff));
+        assert_eq!(FontFormat::detect(b"ttcf...."), Some(FontFormat::Collection));
+        assert_eq!(FontFormat::detect(b"wOFF...."), Some(FontFormat::Woff));
+        assert_eq!(FontFormat::detect(b"wOF2...."), Some(FontFormat::Woff2));
+        assert_eq!(FontFormat::detect(b"%PDF-1.7"), None);
+        assert_eq!(FontFormat::detect(b"OTT"), None);
+    }
+}
@@ -0,0 +1,233 @@
+//! Decoding of WOFF-compressed fonts.
+
+use std::borrow::Cow;
+
+use super::FontFormat;
+
+/// The size of the WOFF header in bytes.
+const HEADER_LEN: usize = 44;
+
+/// The size of a WOFF table directory entry in bytes.
+const ENTRY_LEN: usize = 20;
+
+/// The size of an sfnt table record in bytes.
+const RECORD_LEN: usize = 16;
+
+/// Decode a WOFF 1.0 file into a raw sfnt font program.
+///
+/// Returns `None` if the data is not a well-formed WOFF file.
+pub fn decode(data: &[u8]) -> Option<Vec<u8>> {
+    if FontFormat::detect(data) != Some(FontFormat::Woff) {
+        return None;
+    }
+
+    let flavor = read_u32(data, 4)?;
+    let num_tables = read_u16(data, 12)?;
+    let total_size = read_u32(data, 16)? as usize;
+
+    // Read the table directory.
+    let mut entries = Vec::with_capacity(num_tables as usize);
+    for i in 0..num_tables as usize {
+        let pos = HEADER_LEN + i * ENTRY_LEN;
+        entries.push(Entry {
+            tag: read_u32(data, pos)?,
+            offset: read_u32(data, pos + 4)? as usize,
+            comp_length: read_u32(data, pos + 8)? as usize,
+            orig_length: read_u32(data, pos + 12)? as usize,
+            checksum: read_u32(data, pos + 16)?,
+        });
+    }
+
+    // The sfnt table records must be sorted by tag.
+    entries.sort_by_key(|entry| entry.tag);
+
+    // Write the sfnt offset table.
+    let (search_range, entry_selector, range_shift) = search_params(num_tables);
+
+    // The total size comes from the untrusted header, so we don't reserve
+    // more than the input's size up front and let the buffer grow instead.
+    let mut sfnt = Vec::with_capacity(total_size.min(data.len()));
+    sfnt.extend(flavor.to_be_bytes());
+    sfnt.extend(num_tables.to_be_bytes());
+    sfnt.extend(search_range.to_be_bytes());
+    sfnt.extend(entry_selector.to_be_bytes());
+    sfnt.extend(range_shift.to_be_bytes());
+
+    // Reserve space for the table records, which are filled in below once the
+    // table offsets are known.
+    let records = sfnt.len();
+    sfnt.resize(records + entries.len() * RECORD_LEN, 0);
+
+    for (i, entry) in entries.iter().enumerate() {
+        let end = entry.offset.checked_add(entry.comp_length)?;
+        let stored = data.get(entry.offset..end)?;
+
+        // A table is stored uncompressed if compression didn't make it
+        // smaller.
+        let table = if entry.comp_length < entry.orig_length {
+            Cow::Owned(
+                miniz_oxide::inflate::decompress_to_vec_zlib_with_limit(
+                    stored,
+                    entry.orig_length,
+                )
+                .ok()?,
+            )
+        } else {
+            Cow::Borrowed(stored)
+        };
+
+        if table.len() != entry.orig_length {
+            return None;
+        }
+
+        let offset = u32::try_from(sfnt.len()).ok()?;
+        sfnt.extend_from_slice(&table);
+
+        // Tables are aligned to four bytes.
+        sfnt.resize(align(sfnt.len()), 0);
+
+        let record = &mut sfnt[records + i * RECORD_LEN..][..RECORD_LEN];
+        record[0..4].copy_from_slice(&entry.tag.to_be_bytes());
+        record[4..8].copy_from_slice(&entry.checksum.to_be_bytes());
+        record[8..12].copy_from_slice(&offset.to_be_bytes());
+        record[12..16].copy_from_slice(&(entry.orig_length as u32).to_be_bytes());
+    }
+
+    Some(sfnt)
+}
+
+/// An entry in the WOFF table directory.
+struct Entry {
+    tag: u32,
+    offset: usize,
+    comp_length: usize,
+    orig_length: usize,
+    checksum: u32,
+}
+
+/// Compute the binary search parameters of an sfnt offset table.
+fn search_params(num_tables: u16) -> (u16, u16, u16) {
+    if num_tables == 0 {
+        return (0, 0, 0);
+    }
+
+    let entry_selector = 15 - num_tables.leading_zeros() as u16;
+    let search_range = (1u32 << entry_selector) * RECORD_LEN as u32;
+    let range_shift = u32::from(num_tables) * RECORD_LEN as u32 - search_range;
+    (search_range as u16, entry_selector, range_shift as u16)
+}
+
+/// Round a length up to the next multiple of four.
+fn align(len: usize) -> usize {
+    (len + 3) & !3
+}
+
+/// Read a big-endian `u16` at the given position.
+fn read_u16(data: &[u8], pos: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(data.get(pos..pos + 2)?.try_into().ok()?))
+}
+
+/// Read a big-endian `u32` at the given position.
+fn read_u32(data: &[u8], pos: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(pos..pos + 4)?.try_into().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FONT: &[u8] =
+        include_bytes!("../../../../assets/fonts/NotoSerifHebrew-Regular.ttf");
+
+    /// Encode an sfnt font program as WOFF, compressing every table that gets
+    /// smaller through compression.
+    fn encode(sfnt: &[u8]) -> Vec<u8> {
+        let num_tables = read_u16(sfnt, 4).unwrap();
+        let mut dir = vec![];
+        let mut tables = vec![];
+        let mut offset = HEADER_LEN + num_tables as usize * ENTRY_LEN;
+
+        for i in 0..num_tables as usize {
+            let pos = 12 + i * RECORD_LEN;
+            let tag = read_u32(sfnt, pos).unwrap();
+            let checksum = read_u32(sfnt, pos + 4).unwrap();
+            let start = read_u32(sfnt, pos + 8).unwrap() as usize;
+            let len = read_u32(sfnt, pos + 12).unwrap() as usize;
+            let table = &sfnt[start..start + len];
+
+            let compressed = miniz_oxide::deflate::compress_to_vec_zlib(table, 6);
+            let stored = if compressed.len() < len { compressed } else { table.to_vec() };
+
+            dir.extend(tag.to_be_bytes());
+            dir.extend((offset as u32).to_be_bytes());
+            dir.extend((stored.len() as u32).to_be_bytes());
+            dir.extend((len as u32).to_be_bytes());
+            dir.extend(checksum.to_be_bytes());
+
+            offset += align(stored.len());
+            tables.extend(&stored);
+            tables.resize(align(tables.len()), 0);
+        }
+
+        let mut woff = vec![];
+        woff.extend(b"wOFF");
+        woff.extend(read_u32(sfnt, 0).unwrap().to_be_bytes());
+        woff.extend((offset as u32).to_be_bytes());
+        woff.extend(num_tables.to_be_bytes());
+        woff.extend([0; 2]);
+        woff.extend((sfnt.len() as u32).to_be_bytes());
+        woff.extend([0; 24]);
+        woff.extend(dir);
+        woff.extend(tables);
+        woff
+    }
+
+    #[test]
+    fn test_woff_decode() {
+        let woff = encode(FONT);
+
+        let sfnt = decode(&woff).unwrap();
+        let original = ttf_parser::Face::parse(FONT, 0).unwrap();
+        let decoded = ttf_parser::Face::parse(&sfnt, 0).unwrap();
+        assert_eq!(original.number_of_glyphs(), decoded.number_of_glyphs());
+        for record in original.raw_face().table_records {
+            assert_eq!(
+                original.raw_face().table(record.tag),
+                decoded.raw_face().table(record.tag),
+            );
+        }
+
+        for c in ['א', 'ב', 'ש', 'a'] {
+            assert_eq!(original.glyph_index(c), decoded.glyph_index(c));
+        }
+    }
+
+    #[test]
+    fn test_woff_decode_truncated() {
+        let woff = encode(FONT);
+        assert_eq!(decode(&woff[..woff.len() / 2]), None);
+        assert_eq!(decode(&woff[..HEADER_LEN]), None);
+        assert_eq!(decode(FONT), None);
+    }
+
+    #[test]
+    fn test_woff_decode_forged_size() {
+        // A bogus total size must neither panic nor change the result.
+        let woff = encode(FONT);
+        let mut forged = woff.clone();
+        forged[16..20].copy_from_slice(&u32::MAX.to_be_bytes());
+        assert_eq!(decode(&forged), decode(&woff));
+
+        // A table count that exceeds the data is rejected.
+        let mut forged = woff;
+        forged[12..14].copy_from_slice(&u16::MAX.to_be_bytes());
+        assert_eq!(decode(&forged), None);
+    }
+
+    #[test]
+    fn test_search_params() {
+        assert_eq!(search_params(1), (16, 0, 0));
+        assert_eq!(search_params(10), (128, 3, 32));
+        assert_eq!(search_params(16), (256, 4, 0));
+    }
+}
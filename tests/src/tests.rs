@@ -278,7 +278,7 @@ impl World for TestWorld {
     }
 
     fn font(&self, id: usize) -> Option<Font> {
-        Some(self.fonts[id].clone())
+        self.fonts.get(id).cloned()
     }
 
     fn today(&self, _: Option<i64>) -> Option<Datetime> {